@@ -22,6 +22,10 @@ pub struct DefinitionBook {
 pub struct Definition {
   pub def_id: DefId,
   pub rules: Vec<Rule>,
+  pub span: Span,
+  /// How many of this definition's leading parameters are implicit; callers may
+  /// omit them and have the elaboration pass fill them in with `Era` placeholders.
+  pub implicit_arity: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -29,13 +33,50 @@ pub struct Rule {
   pub def_id: DefId,
   pub pats: Vec<Pattern>,
   pub body: Term,
+  pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub enum Pattern {
-  Ctr(Name, Vec<Pattern>),
-  Var(Option<Name>),
-  Num(u32),
+  Ctr(Name, Vec<Pattern>, Span),
+  Var(Option<Name>, Span),
+  Num(u32, Span),
+  /// A named-field record pattern, e.g. `{x, y}`. A field's sub-pattern of
+  /// `Var(None, _)` binds the field to its own name; `Var(Some(n), _)` renames
+  /// the binding to `n`. Lowered into nested `Dup`s (a `Let` for a single field)
+  /// in the rule body, projecting fields in the same sorted-by-name order
+  /// `Term::Rec` lowers its values in.
+  Rec(Vec<(Name, Pattern)>, Span),
+  /// A positional tuple pattern, e.g. `(x, y)`. Lowered into nested `Dup`s (a
+  /// `Let` for a single element) in the rule body, one projection per element,
+  /// left to right.
+  Tup(Vec<Pattern>, Span),
+}
+
+/// A span of source text, as `(line, column)` pairs, used to point diagnostics and
+/// future LSP features at the text a node originated from (à la Roc's `loc_expr`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+  pub start: (u32, u32),
+  pub end: (u32, u32),
+}
+
+impl fmt::Display for Span {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "|L{}-{}, C{}-{}|", self.start.0, self.end.0, self.start.1, self.end.1)
+  }
+}
+
+impl Pattern {
+  pub fn span(&self) -> Span {
+    match self {
+      Pattern::Ctr(_, _, span) => *span,
+      Pattern::Var(_, span) => *span,
+      Pattern::Num(_, span) => *span,
+      Pattern::Rec(_, span) => *span,
+      Pattern::Tup(_, span) => *span,
+    }
+  }
 }
 
 #[derive(Debug, Clone)]
@@ -43,56 +84,124 @@ pub enum Term {
   Lam {
     nam: Option<Name>,
     bod: Box<Term>,
+    /// Whether this parameter is implicit, rendered as `λ{x} body`. The elaboration
+    /// pass does not read this flag; it fills implicit slots from `Definition::implicit_arity`.
+    implicit: bool,
+    span: Span,
   },
+  /// A bound variable, disambiguated by a De Bruijn-style index: 0 refers to
+  /// the nearest enclosing binder of `nam`, incrementing for each one further out.
   Var {
     nam: Name,
+    idx: usize,
+    span: Span,
   },
   /// Like a scopeless lambda, where the variable can occur outside the body
   Chn {
     nam: Name,
     bod: Box<Term>,
+    span: Span,
   },
   /// The use of a Channel variable.
   Lnk {
     nam: Name,
+    span: Span,
   },
   Let {
     nam: Name,
     val: Box<Term>,
     nxt: Box<Term>,
+    span: Span,
   },
   Ref {
     def_id: DefId,
+    span: Span,
   },
   App {
     fun: Box<Term>,
     arg: Box<Term>,
+    /// Whether `arg` fills an implicit parameter slot, either written as `(f {a} b)`
+    /// at the call site or synthesized by elaboration.
+    implicit: bool,
+    span: Span,
   },
   If {
     cond: Box<Term>,
     then: Box<Term>,
     els_: Box<Term>,
+    span: Span,
   },
   Dup {
     fst: Option<Name>,
     snd: Option<Name>,
     val: Box<Term>,
     nxt: Box<Term>,
+    span: Span,
   },
   Sup {
     fst: Box<Term>,
     snd: Box<Term>,
+    span: Span,
+  },
+  Era {
+    span: Span,
   },
-  Era,
   Num {
-    val: u32,
+    val: Numeric,
+    span: Span,
   },
   /// A numeric operation between built-in numbers.
   Opx {
     op: Op,
     fst: Box<Term>,
     snd: Box<Term>,
+    span: Span,
   },
+  /// An unresolved reference to another source of definitions. Disappears during
+  /// import resolution, replaced by a `Ref` to the definition it names.
+  Import {
+    path: ImportPath,
+    span: Span,
+  },
+  /// A named-field record literal, e.g. `{x = a, y = b}`. Disappears during
+  /// aggregate desugaring, replaced by a `Sup` chain over the fields sorted by
+  /// name (the canonical field order `Pattern::Rec` projects back out).
+  Rec {
+    fields: Vec<(Name, Term)>,
+    span: Span,
+  },
+  /// A positional tuple literal, e.g. `(a, b)`. Disappears during aggregate
+  /// desugaring, replaced by a `Sup` chain over the elements, left to right.
+  Tup {
+    elems: Vec<Term>,
+    span: Span,
+  },
+}
+
+/// Where a `Term::Import` pulls its definitions from, modeled after Dhall's
+/// `Path::File` / `Path::URL`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ImportPath {
+  File(std::path::PathBuf),
+  Url(String),
+}
+
+/// A typed numeric literal, distinguishing the machine-word shapes the HVM
+/// backend supports (mirroring Dhall's `Natural`/`Integer`/`Double` split).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Numeric {
+  /// Unsigned 24-bit integer.
+  U24(u32),
+  /// Signed 24-bit integer.
+  I24(i32),
+  /// 24-bit float.
+  F24(f32),
+}
+
+impl Numeric {
+  pub fn is_float(&self) -> bool {
+    matches!(self, Numeric::F24(_))
+  }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -118,6 +227,11 @@ impl DefinitionBook {
   pub fn new() -> Self {
     Default::default()
   }
+
+  /// The span of the definition named by `def_id`, if it exists.
+  pub fn span_of(&self, def_id: &DefId) -> Option<Span> {
+    self.defs.iter().find(|def| def.def_id == *def_id).map(|def| def.span)
+  }
 }
 
 impl DefNames {
@@ -157,97 +271,285 @@ impl DefNames {
 }
 
 impl Term {
-  pub fn to_string(&self, def_names: &DefNames) -> String {
+  /// The span of text this node originated from. `Span::default()` for terms
+  /// synthesized after parsing without a source node (e.g. by `Term::call`).
+  pub fn span(&self) -> Span {
     match self {
-      Term::Lam { nam, bod } => {
-        format!("λ{} {}", nam.clone().unwrap_or(Name::new("*")), bod.to_string(def_names))
-      }
-      Term::Var { nam } => format!("{nam}"),
-      Term::Chn { nam, bod } => format!("λ${} {}", nam, bod.to_string(def_names)),
-      Term::Lnk { nam } => format!("${nam}"),
-      Term::Let { nam, val, nxt } => {
-        format!("let {} = {}; {}", nam, val.to_string(def_names), nxt.to_string(def_names))
-      }
-      Term::Ref { def_id } => format!("{}", def_names.name(def_id).unwrap()),
-      Term::App { fun, arg } => format!("({} {})", fun.to_string(def_names), arg.to_string(def_names)),
-      Term::If { cond, then, els_ } => {
+      Term::Lam { span, .. }
+      | Term::Var { span, .. }
+      | Term::Chn { span, .. }
+      | Term::Lnk { span, .. }
+      | Term::Let { span, .. }
+      | Term::Ref { span, .. }
+      | Term::App { span, .. }
+      | Term::If { span, .. }
+      | Term::Dup { span, .. }
+      | Term::Sup { span, .. }
+      | Term::Era { span }
+      | Term::Num { span, .. }
+      | Term::Opx { span, .. }
+      | Term::Import { span, .. }
+      | Term::Rec { span, .. }
+      | Term::Tup { span, .. } => *span,
+    }
+  }
+
+  pub fn to_string(&self, def_names: &DefNames) -> String {
+    self.to_string_impl(def_names, false)
+  }
+
+  /// Like `to_string`, but with each node's span rendered inline as `|Lx-y, Cx-y|`.
+  /// Opt-in, for diagnostics; the default `to_string`/`Display` ignore spans.
+  pub fn to_string_annotated(&self, def_names: &DefNames) -> String {
+    self.to_string_impl(def_names, true)
+  }
+
+  fn to_string_impl(&self, def_names: &DefNames, annotated: bool) -> String {
+    let s = match self {
+      Term::Lam { nam, bod, implicit, .. } => {
+        let nam = nam.clone().unwrap_or(Name::new("*"));
+        if *implicit {
+          format!("λ{{{}}} {}", nam, bod.to_string_impl(def_names, annotated))
+        } else {
+          format!("λ{} {}", nam, bod.to_string_impl(def_names, annotated))
+        }
+      }
+      Term::Var { nam, .. } => format!("{nam}"),
+      Term::Chn { nam, bod, .. } => format!("λ${} {}", nam, bod.to_string_impl(def_names, annotated)),
+      Term::Lnk { nam, .. } => format!("${nam}"),
+      Term::Let { nam, val, nxt, .. } => {
+        format!(
+          "let {} = {}; {}",
+          nam,
+          val.to_string_impl(def_names, annotated),
+          nxt.to_string_impl(def_names, annotated)
+        )
+      }
+      Term::Ref { def_id, .. } => format!("{}", def_names.name(def_id).unwrap()),
+      Term::App { fun, arg, implicit, .. } => {
+        if *implicit {
+          format!("({} {{{}}})", fun.to_string_impl(def_names, annotated), arg.to_string_impl(def_names, annotated))
+        } else {
+          format!("({} {})", fun.to_string_impl(def_names, annotated), arg.to_string_impl(def_names, annotated))
+        }
+      }
+      Term::If { cond, then, els_, .. } => {
         format!(
           "if {} then {} else {}",
-          cond.to_string(def_names),
-          then.to_string(def_names),
-          els_.to_string(def_names)
+          cond.to_string_impl(def_names, annotated),
+          then.to_string_impl(def_names, annotated),
+          els_.to_string_impl(def_names, annotated)
         )
       }
-      Term::Dup { fst, snd, val, nxt } => format!(
+      Term::Dup { fst, snd, val, nxt, .. } => format!(
         "dup {} {} = {}; {}",
         fst.as_ref().map(|x| x.as_str()).unwrap_or("*"),
         snd.as_ref().map(|x| x.as_str()).unwrap_or("*"),
-        val.to_string(def_names),
-        nxt.to_string(def_names)
+        val.to_string_impl(def_names, annotated),
+        nxt.to_string_impl(def_names, annotated)
       ),
-      Term::Sup { fst, snd } => format!("{{{} {}}}", fst.to_string(def_names), snd.to_string(def_names)),
-      Term::Era => "*".to_string(),
-      Term::Num { val } => format!("{val}"),
-      Term::Opx { op, fst, snd } => {
-        format!("({} {} {})", op, fst.to_string(def_names), snd.to_string(def_names))
+      Term::Sup { fst, snd, .. } => {
+        format!("{{{} {}}}", fst.to_string_impl(def_names, annotated), snd.to_string_impl(def_names, annotated))
       }
-    }
+      Term::Era { .. } => "*".to_string(),
+      Term::Num { val, .. } => format!("{val}"),
+      Term::Opx { op, fst, snd, .. } => {
+        format!("({} {} {})", op, fst.to_string_impl(def_names, annotated), snd.to_string_impl(def_names, annotated))
+      }
+      Term::Import { path, .. } => format!("import \"{path}\""),
+      Term::Rec { fields, .. } => {
+        format!("{{{}}}", fields.iter().map(|(nam, val)| format!("{nam} = {}", val.to_string_impl(def_names, annotated))).join(", "))
+      }
+      Term::Tup { elems, .. } => {
+        format!("({})", elems.iter().map(|x| x.to_string_impl(def_names, annotated)).join(", "))
+      }
+    };
+    if annotated { format!("{s}{}", self.span()) } else { s }
   }
 
   /// Make a call term by folding args around a called function term with applications.
+  /// The resulting `App` nodes are explicit and synthetic, so they carry no span of their own.
   pub fn call(called: Term, args: impl IntoIterator<Item = Term>) -> Self {
-    args.into_iter().fold(called, |acc, arg| Term::App { fun: Box::new(acc), arg: Box::new(arg) })
+    args.into_iter().fold(called, |acc, arg| Term::App {
+      fun: Box::new(acc),
+      arg: Box::new(arg),
+      implicit: false,
+      span: Span::default(),
+    })
+  }
+
+  /// Shifts the De Bruijn index of every free occurrence of a variable named
+  /// `cutoff.0` whose index is at least `cutoff.1`, by `delta`. Descending into a
+  /// binder of that same name increments the cutoff, since one more binder of
+  /// that name now separates the root from the occurrence.
+  pub fn shift(&mut self, delta: isize, cutoff: (Name, usize)) {
+    match self {
+      Term::Var { nam, idx, .. } => {
+        if *nam == cutoff.0 && *idx >= cutoff.1 {
+          *idx = (*idx as isize + delta) as usize;
+        }
+      }
+      Term::Lam { nam, bod, .. } => {
+        let cutoff = shadow(nam.as_ref(), cutoff);
+        bod.shift(delta, cutoff);
+      }
+      // Chn/Lnk are scopeless, so occurrences are always free and never shifted.
+      Term::Chn { bod, .. } => bod.shift(delta, cutoff),
+      Term::Lnk { .. } => (),
+      Term::Let { nam, val, nxt, .. } => {
+        val.shift(delta, cutoff.clone());
+        nxt.shift(delta, shadow(Some(nam), cutoff));
+      }
+      Term::If { cond, then, els_, .. } => {
+        cond.shift(delta, cutoff.clone());
+        then.shift(delta, cutoff.clone());
+        els_.shift(delta, cutoff);
+      }
+      Term::Ref { .. } | Term::Era { .. } | Term::Num { .. } | Term::Import { .. } => (),
+      Term::App { fun, arg, .. } => {
+        fun.shift(delta, cutoff.clone());
+        arg.shift(delta, cutoff);
+      }
+      Term::Dup { fst, snd, val, nxt, .. } => {
+        val.shift(delta, cutoff.clone());
+        // A single `Dup` introduces one binder even when `fst`/`snd` share a name,
+        // matching `subst`'s "fst matches OR snd matches" treatment below.
+        let cutoff = if fst.as_ref().is_some_and(|fst| *fst == cutoff.0) || snd.as_ref().is_some_and(|snd| *snd == cutoff.0) {
+          (cutoff.0, cutoff.1 + 1)
+        } else {
+          cutoff
+        };
+        nxt.shift(delta, cutoff);
+      }
+      Term::Sup { fst, snd, .. } => {
+        fst.shift(delta, cutoff.clone());
+        snd.shift(delta, cutoff);
+      }
+      Term::Opx { fst, snd, .. } => {
+        fst.shift(delta, cutoff.clone());
+        snd.shift(delta, cutoff);
+      }
+      Term::Rec { fields, .. } => {
+        for (_, val) in fields {
+          val.shift(delta, cutoff.clone());
+        }
+      }
+      Term::Tup { elems, .. } => {
+        for elem in elems {
+          elem.shift(delta, cutoff.clone());
+        }
+      }
+    }
   }
 
-  /// Substitute the occurences of a variable in a term with the given term.
-  pub fn subst(&mut self, from: &Name, to: &Term) {
+  /// Substitute the occurrences of the variable `var` (name and De Bruijn index)
+  /// in a term with the given term, avoiding capture by shifting `to` across
+  /// binders instead of requiring globally fresh names (following Dhall).
+  pub fn subst(&mut self, var: (Name, usize), to: &Term) {
     match self {
-      Term::Lam { nam: Some(nam), .. } if nam == from => (),
-      Term::Lam { bod, .. } => bod.subst(from, to),
-      Term::Var { nam } if nam == from => *self = to.clone(),
+      Term::Var { nam, idx, .. } if *nam == var.0 && *idx == var.1 => *self = to.clone(),
       Term::Var { .. } => (),
-      // Only substitute scoped variables.
-      Term::Chn { bod, .. } => bod.subst(from, to),
+      Term::Lam { nam, bod, .. } => {
+        // Entering *any* named binder requires shifting `to` by that binder's own
+        // name, since `to` is moving one scope deeper under it regardless of
+        // whether the binder happens to share `var`'s name; the De Bruijn index
+        // in `var` only advances when it does.
+        let shifted = nam.as_ref().map_or_else(|| to.clone(), |nam| shift_into(to, nam));
+        let var = if nam.as_ref().is_some_and(|nam| *nam == var.0) { enter(var) } else { var };
+        bod.subst(var, &shifted)
+      }
+      // Chn/Lnk are scopeless (see `shift`), so entering one never shifts `to`.
+      Term::Chn { bod, .. } => bod.subst(var, to),
       Term::Lnk { .. } => (),
-      Term::Let { nam, val, nxt } => {
-        val.subst(from, to);
-        if nam != from {
-          nxt.subst(from, to);
+      Term::Let { nam, val, nxt, .. } => {
+        val.subst(var.clone(), to);
+        let shifted = shift_into(to, nam);
+        if *nam == var.0 {
+          nxt.subst(enter(var.clone()), &shifted);
+        } else {
+          nxt.subst(var, &shifted);
         }
       }
-      Term::If { cond, then, els_ } => {
-        cond.subst(from, to);
-        then.subst(from, to);
-        els_.subst(from, to);
+      Term::If { cond, then, els_, .. } => {
+        cond.subst(var.clone(), to);
+        then.subst(var.clone(), to);
+        els_.subst(var, to);
       }
       Term::Ref { .. } => (),
-      Term::App { fun, arg } => {
-        fun.subst(from, to);
-        arg.subst(from, to);
-      }
-      Term::Dup { fst, snd, val, nxt } => {
-        val.subst(from, to);
-        if fst.as_ref().map_or(true, |fst| fst != from) && snd.as_ref().map_or(true, |snd| snd != from) {
-          nxt.subst(from, to);
+      Term::App { fun, arg, .. } => {
+        fun.subst(var.clone(), to);
+        arg.subst(var, to);
+      }
+      Term::Dup { fst, snd, val, nxt, .. } => {
+        val.subst(var.clone(), to);
+        // Shift `to` once per distinct binder name; `fst`/`snd` sharing a name
+        // introduce a single binder (see `shift`'s matching treatment), so that
+        // name must only be shifted once, not twice.
+        let mut shifted = to.clone();
+        match (fst.as_ref(), snd.as_ref()) {
+          (Some(fst), Some(snd)) if fst == snd => shifted = shift_into(&shifted, fst),
+          (Some(fst), Some(snd)) => {
+            shifted = shift_into(&shifted, fst);
+            shifted = shift_into(&shifted, snd);
+          }
+          (Some(nam), None) | (None, Some(nam)) => shifted = shift_into(&shifted, nam),
+          (None, None) => {}
+        }
+        if fst.as_ref().is_some_and(|fst| *fst == var.0) || snd.as_ref().is_some_and(|snd| *snd == var.0) {
+          nxt.subst(enter(var.clone()), &shifted);
+        } else {
+          nxt.subst(var, &shifted);
         }
       }
-      Term::Sup { fst, snd } => {
-        fst.subst(from, to);
-        snd.subst(from, to);
+      Term::Sup { fst, snd, .. } => {
+        fst.subst(var.clone(), to);
+        snd.subst(var, to);
       }
-      Term::Era => (),
+      Term::Era { .. } => (),
       Term::Num { .. } => (),
       Term::Opx { fst, snd, .. } => {
-        fst.subst(from, to);
-        snd.subst(from, to);
+        fst.subst(var.clone(), to);
+        snd.subst(var, to);
+      }
+      Term::Import { .. } => (),
+      Term::Rec { fields, .. } => {
+        for (_, val) in fields {
+          val.subst(var.clone(), to);
+        }
+      }
+      Term::Tup { elems, .. } => {
+        for elem in elems {
+          elem.subst(var.clone(), to);
+        }
       }
     }
   }
 }
 
+/// Increments a cutoff's index if `binder` shadows its name, since one more
+/// binder of that name now sits between the root and any occurrence below.
+fn shadow(binder: Option<&Name>, cutoff: (Name, usize)) -> (Name, usize) {
+  match binder {
+    Some(nam) if *nam == cutoff.0 => (cutoff.0, cutoff.1 + 1),
+    _ => cutoff,
+  }
+}
+
+/// The target `(name, index)` to recurse with once a binder of `var.0` has been entered.
+fn enter((nam, idx): (Name, usize)) -> (Name, usize) {
+  (nam, idx + 1)
+}
+
+/// Shifts `to` up by one for `nam`, since it is moving under a fresh binder of that name.
+fn shift_into(to: &Term, nam: &Name) -> Term {
+  let mut to = to.clone();
+  to.shift(1, (nam.clone(), 0));
+  to
+}
+
 impl Rule {
   pub fn to_string(&self, def_names: &DefNames) -> String {
-    let Rule { def_id, pats, body } = self;
+    let Rule { def_id, pats, body, .. } = self;
     format!(
       "({}{}) = {}",
       def_names.name(def_id).unwrap(),
@@ -273,10 +575,19 @@ impl Definition {
 
 impl From<&Pattern> for Term {
   fn from(value: &Pattern) -> Self {
+    let span = value.span();
     match value {
-      Pattern::Ctr(nam, args) => Term::call(Term::Var { nam: nam.clone() }, args.iter().map(Term::from)),
-      Pattern::Var(nam) => Term::Var { nam: Name::new(nam.as_ref().map(|x| x.as_str()).unwrap_or("_")) },
-      Pattern::Num(num) => Term::Num { val: *num },
+      Pattern::Ctr(nam, args, _) => {
+        Term::call(Term::Var { nam: nam.clone(), idx: 0, span }, args.iter().map(Term::from))
+      }
+      Pattern::Var(nam, _) => {
+        Term::Var { nam: Name::new(nam.as_ref().map(|x| x.as_str()).unwrap_or("_")), idx: 0, span }
+      }
+      Pattern::Num(num, _) => Term::Num { val: Numeric::U24(*num), span },
+      Pattern::Rec(fields, _) => {
+        Term::Rec { fields: fields.iter().map(|(nam, pat)| (nam.clone(), Term::from(pat))).collect(), span }
+      }
+      Pattern::Tup(elems, _) => Term::Tup { elems: elems.iter().map(Term::from).collect(), span },
     }
   }
 }
@@ -290,9 +601,11 @@ impl fmt::Display for DefinitionBook {
 impl fmt::Display for Pattern {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
-      Pattern::Ctr(name, pats) => write!(f, "({}{})", name, pats.iter().map(|p| format!(" {p}")).join("")),
-      Pattern::Var(nam) => write!(f, "{}", nam.as_ref().map(|x| x.as_str()).unwrap_or("*")),
-      Pattern::Num(num) => write!(f, "{num}"),
+      Pattern::Ctr(name, pats, _) => write!(f, "({}{})", name, pats.iter().map(|p| format!(" {p}")).join("")),
+      Pattern::Var(nam, _) => write!(f, "{}", nam.as_ref().map(|x| x.as_str()).unwrap_or("*")),
+      Pattern::Num(num, _) => write!(f, "{num}"),
+      Pattern::Rec(fields, _) => write!(f, "{{{}}}", fields.iter().map(|(nam, pat)| format!("{nam} = {pat}")).join(", ")),
+      Pattern::Tup(elems, _) => write!(f, "({})", elems.iter().map(|p| p.to_string()).join(", ")),
     }
   }
 }
@@ -318,3 +631,710 @@ impl fmt::Display for Op {
     }
   }
 }
+
+impl fmt::Display for ImportPath {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ImportPath::File(path) => write!(f, "{}", path.display()),
+      ImportPath::Url(url) => write!(f, "{url}"),
+    }
+  }
+}
+
+/// An error raised while resolving `Term::Import` nodes into local definitions.
+#[derive(Debug)]
+pub enum ImportError {
+  /// `path` was reached again while still being resolved.
+  Cycle(ImportPath),
+  /// Loading the source at `path` failed, with the loader's error message.
+  Load(ImportPath, String),
+  /// Namespacing `name` while merging `path` still collided with a name already
+  /// in the book (e.g. two distinct imports producing the same namespaced name).
+  NameCollision(ImportPath, Name),
+}
+
+impl fmt::Display for ImportError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ImportError::Cycle(path) => write!(f, "import cycle detected at '{path}'"),
+      ImportError::Load(path, msg) => write!(f, "failed to import '{path}': {msg}"),
+      ImportError::NameCollision(path, name) => {
+        write!(f, "importing '{path}' would redefine '{name}', which already exists")
+      }
+    }
+  }
+}
+
+/// Recursively resolves every `Term::Import` reachable from `book`, merging the
+/// referenced `Definition`s into `book` and rewriting each import site into a
+/// `Term::Ref` pointing at the newly-inserted entry point (the imported book's
+/// first definition).
+///
+/// `load` turns an `ImportPath` into a parsed `DefinitionBook`; reading the file or
+/// fetching the URL is left to the caller (e.g. the CLI's parser front-end). A path
+/// imported more than once (a diamond) is only loaded and merged once; reaching a
+/// path that is still being resolved is rejected as a cycle.
+pub fn resolve_imports(
+  book: &mut DefinitionBook,
+  load: &mut impl FnMut(&ImportPath) -> Result<DefinitionBook, String>,
+) -> Result<(), ImportError> {
+  let mut resolved = std::collections::HashMap::<ImportPath, DefId>::new();
+  let mut in_progress = Vec::<ImportPath>::new();
+  let mut def_idx = 0;
+  while def_idx < book.defs.len() {
+    let mut rule_idx = 0;
+    while rule_idx < book.defs[def_idx].rules.len() {
+      let mut body = std::mem::replace(&mut book.defs[def_idx].rules[rule_idx].body, Term::Era { span: Span::default() });
+      resolve_term_imports(&mut body, book, load, &mut resolved, &mut in_progress)?;
+      book.defs[def_idx].rules[rule_idx].body = body;
+      rule_idx += 1;
+    }
+    def_idx += 1;
+  }
+  Ok(())
+}
+
+fn resolve_term_imports(
+  term: &mut Term,
+  book: &mut DefinitionBook,
+  load: &mut impl FnMut(&ImportPath) -> Result<DefinitionBook, String>,
+  resolved: &mut std::collections::HashMap<ImportPath, DefId>,
+  in_progress: &mut Vec<ImportPath>,
+) -> Result<(), ImportError> {
+  if let Term::Import { path, span } = term {
+    let span = *span;
+    let def_id = match resolved.get(path) {
+      Some(def_id) => *def_id,
+      None => {
+        if in_progress.contains(path) {
+          return Err(ImportError::Cycle(path.clone()));
+        }
+        in_progress.push(path.clone());
+        let imported = load(path).map_err(|msg| ImportError::Load(path.clone(), msg))?;
+        let def_id = merge_imported_book(book, imported, path, load, resolved, in_progress)?;
+        in_progress.pop();
+        resolved.insert(path.clone(), def_id);
+        def_id
+      }
+    };
+    *term = Term::Ref { def_id, span };
+    return Ok(());
+  }
+  match term {
+    Term::Lam { bod, .. } | Term::Chn { bod, .. } => resolve_term_imports(bod, book, load, resolved, in_progress)?,
+    Term::Let { val, nxt, .. } => {
+      resolve_term_imports(val, book, load, resolved, in_progress)?;
+      resolve_term_imports(nxt, book, load, resolved, in_progress)?;
+    }
+    Term::If { cond, then, els_, .. } => {
+      resolve_term_imports(cond, book, load, resolved, in_progress)?;
+      resolve_term_imports(then, book, load, resolved, in_progress)?;
+      resolve_term_imports(els_, book, load, resolved, in_progress)?;
+    }
+    Term::App { fun, arg, .. } => {
+      resolve_term_imports(fun, book, load, resolved, in_progress)?;
+      resolve_term_imports(arg, book, load, resolved, in_progress)?;
+    }
+    Term::Dup { val, nxt, .. } => {
+      resolve_term_imports(val, book, load, resolved, in_progress)?;
+      resolve_term_imports(nxt, book, load, resolved, in_progress)?;
+    }
+    Term::Sup { fst, snd, .. } | Term::Opx { fst, snd, .. } => {
+      resolve_term_imports(fst, book, load, resolved, in_progress)?;
+      resolve_term_imports(snd, book, load, resolved, in_progress)?;
+    }
+    Term::Rec { fields, .. } => {
+      for (_, val) in fields {
+        resolve_term_imports(val, book, load, resolved, in_progress)?;
+      }
+    }
+    Term::Tup { elems, .. } => {
+      for elem in elems {
+        resolve_term_imports(elem, book, load, resolved, in_progress)?;
+      }
+    }
+    Term::Var { .. } | Term::Lnk { .. } | Term::Ref { .. } | Term::Era { .. } | Term::Num { .. } | Term::Import { .. } => (),
+  }
+  Ok(())
+}
+
+/// Derives an import's namespace prefix from its full path rather than just its
+/// final component, so two imports that merely share a basename (e.g.
+/// `./vendor/foo.bend` and `./lib/foo.bend`) don't collide on the same prefix.
+fn namespace_for(path: &ImportPath) -> String {
+  match path {
+    ImportPath::File(path) => {
+      let path = path.with_extension("");
+      path.components().map(|c| c.as_os_str().to_string_lossy()).filter(|c| c != "." && c != "..").join(".")
+    }
+    ImportPath::Url(url) => url.replace(['.', ':', '/'], "_"),
+  }
+}
+
+/// Namespaces and appends `imported`'s definitions onto `book`, then resolves any
+/// imports nested inside them, returning the `DefId` of the imported entry point.
+fn merge_imported_book(
+  book: &mut DefinitionBook,
+  imported: DefinitionBook,
+  path: &ImportPath,
+  load: &mut impl FnMut(&ImportPath) -> Result<DefinitionBook, String>,
+  resolved: &mut std::collections::HashMap<ImportPath, DefId>,
+  in_progress: &mut Vec<ImportPath>,
+) -> Result<DefId, ImportError> {
+  let namespace = namespace_for(path);
+  let mut entry_point = None;
+  let first_new_def = book.defs.len();
+  for mut def in imported.defs {
+    let old_name = imported.def_names.name(&def.def_id).unwrap();
+    let namespaced = Name::new(&format!("{namespace}.{old_name}"));
+    if book.def_names.contains_name(&namespaced) {
+      return Err(ImportError::NameCollision(path.clone(), namespaced));
+    }
+    let new_id = book.def_names.insert(namespaced);
+    for rule in &mut def.rules {
+      rule.def_id = new_id;
+    }
+    def.def_id = new_id;
+    entry_point.get_or_insert(new_id);
+    book.defs.push(def);
+  }
+  let mut def_idx = first_new_def;
+  while def_idx < book.defs.len() {
+    let mut rule_idx = 0;
+    while rule_idx < book.defs[def_idx].rules.len() {
+      let mut body = std::mem::replace(&mut book.defs[def_idx].rules[rule_idx].body, Term::Era { span: Span::default() });
+      resolve_term_imports(&mut body, book, load, resolved, in_progress)?;
+      book.defs[def_idx].rules[rule_idx].body = body;
+      rule_idx += 1;
+    }
+    def_idx += 1;
+  }
+  Ok(entry_point.expect("imported book has no definitions"))
+}
+
+impl fmt::Display for Numeric {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Numeric::U24(val) => write!(f, "{val}"),
+      Numeric::I24(val) => write!(f, "{val:+}"),
+      Numeric::F24(val) => write!(f, "{val}"),
+    }
+  }
+}
+
+/// An error raised when an `Opx` is applied to operands of incompatible or
+/// unsupported types.
+#[derive(Debug)]
+pub enum OpError {
+  /// `op` only makes sense for integer operands (the bitwise ops, `%`).
+  FloatUnsupported(Op),
+  /// The two operands of an `Opx` have different numeric types.
+  MixedTypes(Numeric, Numeric),
+  /// `Op::DIV` or `Op::MOD` on integer operands with a zero divisor. Float `DIV`
+  /// by zero is not an error; it yields an infinity or NaN.
+  DivByZero(Op),
+  /// `Op::LSH`/`Op::RSH` with a shift amount outside `0..32` (operands are
+  /// `U24`/`I24`, ultimately truncated back to 32-bit words).
+  ShiftOverflow(Op, i64),
+}
+
+impl fmt::Display for OpError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      OpError::FloatUnsupported(op) => write!(f, "operator '{op}' does not support float operands"),
+      OpError::MixedTypes(fst, snd) => write!(f, "mismatched numeric types in operation: {fst} and {snd}"),
+      OpError::DivByZero(op) => write!(f, "division by zero in '{op}' operation"),
+      OpError::ShiftOverflow(op, amount) => write!(f, "shift amount {amount} out of range for '{op}' operation"),
+    }
+  }
+}
+
+/// Evaluates a binary numeric operation, routing integer operands (`U24`/`I24`)
+/// through integer arithmetic and `F24` operands through float arithmetic. The
+/// bitwise ops and `%` are integer-only and fail on float operands; mixing an
+/// integer operand with a float one is always an error.
+pub fn eval_opx(op: Op, fst: Numeric, snd: Numeric) -> Result<Numeric, OpError> {
+  match (fst, snd) {
+    (Numeric::F24(a), Numeric::F24(b)) => eval_float_op(op, a, b),
+    (Numeric::U24(a), Numeric::U24(b)) => eval_int_op(op, a as i64, b as i64).map(|v| Numeric::U24(v as u32)),
+    (Numeric::I24(a), Numeric::I24(b)) => eval_int_op(op, a as i64, b as i64).map(|v| Numeric::I24(v as i32)),
+    _ => Err(OpError::MixedTypes(fst, snd)),
+  }
+}
+
+fn eval_int_op(op: Op, a: i64, b: i64) -> Result<i64, OpError> {
+  Ok(match op {
+    Op::ADD => a + b,
+    Op::SUB => a - b,
+    Op::MUL => a * b,
+    Op::DIV | Op::MOD if b == 0 => return Err(OpError::DivByZero(op)),
+    Op::DIV => a / b,
+    Op::MOD => a % b,
+    Op::EQ => (a == b) as i64,
+    Op::NE => (a != b) as i64,
+    Op::LT => (a < b) as i64,
+    Op::GT => (a > b) as i64,
+    Op::AND => a & b,
+    Op::OR => a | b,
+    Op::XOR => a ^ b,
+    Op::NOT => !a,
+    // Operands are truncated back to 32-bit words (`U24`/`I24` are `u32`/`i32`),
+    // so a shift of 32 or more would silently compute in widened `i64` space
+    // and then get chopped off rather than erroring or reaching zero correctly.
+    Op::LSH | Op::RSH if !(0..32).contains(&b) => return Err(OpError::ShiftOverflow(op, b)),
+    Op::LSH => a << b,
+    Op::RSH => a >> b,
+  })
+}
+
+fn eval_float_op(op: Op, a: f32, b: f32) -> Result<Numeric, OpError> {
+  match op {
+    Op::ADD => Ok(Numeric::F24(a + b)),
+    Op::SUB => Ok(Numeric::F24(a - b)),
+    Op::MUL => Ok(Numeric::F24(a * b)),
+    Op::DIV => Ok(Numeric::F24(a / b)),
+    Op::EQ => Ok(Numeric::U24((a == b) as u32)),
+    Op::NE => Ok(Numeric::U24((a != b) as u32)),
+    Op::LT => Ok(Numeric::U24((a < b) as u32)),
+    Op::GT => Ok(Numeric::U24((a > b) as u32)),
+    Op::MOD | Op::AND | Op::OR | Op::XOR | Op::NOT | Op::LSH | Op::RSH => Err(OpError::FloatUnsupported(op)),
+  }
+}
+
+/// Walks every rule body in `book`, statically evaluating any `Opx` whose operands
+/// are both numeric literals. This catches mixed-type operands (e.g. a float added
+/// to an integer) and integer-only ops applied to floats at the book level, with a
+/// clear error, instead of letting them reach the HVM backend as wrong combinators.
+pub fn check_book_numeric_ops(book: &DefinitionBook) -> Result<(), OpError> {
+  for def in &book.defs {
+    for rule in &def.rules {
+      check_term_numeric_ops(&rule.body)?;
+    }
+  }
+  Ok(())
+}
+
+fn check_term_numeric_ops(term: &Term) -> Result<(), OpError> {
+  match term {
+    Term::Opx { op, fst, snd, .. } => {
+      check_term_numeric_ops(fst)?;
+      check_term_numeric_ops(snd)?;
+      if let (Term::Num { val: fst, .. }, Term::Num { val: snd, .. }) = (fst.as_ref(), snd.as_ref()) {
+        eval_opx(*op, *fst, *snd)?;
+      }
+      Ok(())
+    }
+    Term::Lam { bod, .. } | Term::Chn { bod, .. } => check_term_numeric_ops(bod),
+    Term::Let { val, nxt, .. } => {
+      check_term_numeric_ops(val)?;
+      check_term_numeric_ops(nxt)
+    }
+    Term::If { cond, then, els_, .. } => {
+      check_term_numeric_ops(cond)?;
+      check_term_numeric_ops(then)?;
+      check_term_numeric_ops(els_)
+    }
+    Term::App { fun, arg, .. } => {
+      check_term_numeric_ops(fun)?;
+      check_term_numeric_ops(arg)
+    }
+    Term::Dup { val, nxt, .. } => {
+      check_term_numeric_ops(val)?;
+      check_term_numeric_ops(nxt)
+    }
+    Term::Sup { fst, snd, .. } => {
+      check_term_numeric_ops(fst)?;
+      check_term_numeric_ops(snd)
+    }
+    Term::Rec { fields, .. } => {
+      for (_, val) in fields {
+        check_term_numeric_ops(val)?;
+      }
+      Ok(())
+    }
+    Term::Tup { elems, .. } => {
+      for elem in elems {
+        check_term_numeric_ops(elem)?;
+      }
+      Ok(())
+    }
+    Term::Var { .. } | Term::Lnk { .. } | Term::Ref { .. } | Term::Era { .. } | Term::Num { .. } | Term::Import { .. } => {
+      Ok(())
+    }
+  }
+}
+
+/// Elaborates implicit arguments: for every call `(f a1 .. an)` whose head is a
+/// `Ref` to a definition with a nonzero `implicit_arity`, if fewer arguments were
+/// supplied than the definition's arity, synthesizes `Era` placeholders for the
+/// missing leading implicit slots so the call still lines up positionally.
+/// Following Kind, this lets library authors hide plumbing parameters from callers.
+pub fn elaborate_implicits(book: &mut DefinitionBook) {
+  let mut def_idx = 0;
+  while def_idx < book.defs.len() {
+    let mut rule_idx = 0;
+    while rule_idx < book.defs[def_idx].rules.len() {
+      let mut body = std::mem::replace(&mut book.defs[def_idx].rules[rule_idx].body, Term::Era { span: Span::default() });
+      elaborate_term(&mut body, book);
+      book.defs[def_idx].rules[rule_idx].body = body;
+      rule_idx += 1;
+    }
+    def_idx += 1;
+  }
+}
+
+fn elaborate_term(term: &mut Term, book: &DefinitionBook) {
+  match term {
+    Term::Lam { bod, .. } | Term::Chn { bod, .. } => elaborate_term(bod, book),
+    Term::Let { val, nxt, .. } => {
+      elaborate_term(val, book);
+      elaborate_term(nxt, book);
+    }
+    Term::If { cond, then, els_, .. } => {
+      elaborate_term(cond, book);
+      elaborate_term(then, book);
+      elaborate_term(els_, book);
+    }
+    Term::Dup { val, nxt, .. } => {
+      elaborate_term(val, book);
+      elaborate_term(nxt, book);
+    }
+    Term::Sup { fst, snd, .. } | Term::Opx { fst, snd, .. } => {
+      elaborate_term(fst, book);
+      elaborate_term(snd, book);
+    }
+    Term::App { .. } => {
+      let (mut head, args) = take_spine(term);
+      elaborate_term(&mut head, book);
+      let mut args: Vec<(Term, bool, Span)> = args
+        .into_iter()
+        .map(|(mut arg, implicit, span)| {
+          elaborate_term(&mut arg, book);
+          (arg, implicit, span)
+        })
+        .collect();
+      if let Some((needed, span)) = implicit_placeholders_needed(&head, &args, book) {
+        let placeholders = (0..needed).map(|_| (Term::Era { span }, true, span));
+        args.splice(0..0, placeholders);
+      }
+      *term = args.into_iter().fold(head, |acc, (arg, implicit, span)| {
+        Term::App { fun: Box::new(acc), arg: Box::new(arg), implicit, span }
+      });
+    }
+    Term::Rec { fields, .. } => {
+      for (_, val) in fields {
+        elaborate_term(val, book);
+      }
+    }
+    Term::Tup { elems, .. } => {
+      for elem in elems {
+        elaborate_term(elem, book);
+      }
+    }
+    Term::Var { .. } | Term::Lnk { .. } | Term::Ref { .. } | Term::Era { .. } | Term::Num { .. } | Term::Import { .. } => (),
+  }
+}
+
+/// If `head` refers to a definition with leading implicit parameters still
+/// missing from `args`, returns how many placeholders to splice in front and
+/// the span to give them. Args the caller already marked `implicit` (e.g. a
+/// manually-written `(f {a} b)`) count against that leading quota instead of
+/// being padded out again.
+fn implicit_placeholders_needed(head: &Term, args: &[(Term, bool, Span)], book: &DefinitionBook) -> Option<(usize, Span)> {
+  let Term::Ref { def_id, span } = head else { return None };
+  let def = book.defs.iter().find(|d| d.def_id == *def_id)?;
+  let leading_implicit = args.iter().take_while(|(_, implicit, _)| *implicit).count().min(def.implicit_arity);
+  let needed = def.implicit_arity - leading_implicit;
+  (needed > 0 && args.len() < def.arity()).then_some((needed, *span))
+}
+
+/// Decomposes an application spine `((f a1) a2) .. an` into its head and the list
+/// of arguments applied to it, left to right, each paired with its own `App`'s
+/// `implicit` flag and span (so rebuilding the spine doesn't lose either).
+fn take_spine(term: &mut Term) -> (Term, Vec<(Term, bool, Span)>) {
+  let mut args = Vec::new();
+  let mut cur = std::mem::replace(term, Term::Era { span: Span::default() });
+  loop {
+    match cur {
+      Term::App { fun, arg, implicit, span } => {
+        args.push((*arg, implicit, span));
+        cur = *fun;
+      }
+      head => {
+        args.reverse();
+        return (head, args);
+      }
+    }
+  }
+}
+
+/// An error raised while desugaring `Pattern::Rec`/`Pattern::Tup` field patterns.
+#[derive(Debug)]
+pub enum DesugarError {
+  /// A record/tuple field's sub-pattern performs further destructuring (e.g.
+  /// `{x = Some(y)}`), which the `Dup`-chain lowering can neither check nor bind.
+  UnsupportedNestedPattern(Pattern),
+}
+
+impl fmt::Display for DesugarError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DesugarError::UnsupportedNestedPattern(pat) => {
+        write!(f, "field pattern '{pat}' nests further destructuring, which record/tuple patterns do not support")
+      }
+    }
+  }
+}
+
+/// Desugars every `Term::Rec`/`Term::Tup` and `Pattern::Rec`/`Pattern::Tup` in
+/// `book`, giving users Roc-style named records and positional tuples without
+/// requiring them to hand-encode combinator pairs. Literals lower into nested
+/// `Sup` trees; patterns lower into nested `Dup`s (or a `Let`, for a single
+/// field) wrapped around the rule's body, with the pattern slot itself replaced
+/// by a plain `Var` bound to the matched value. A field pattern that nests
+/// further destructuring (a `Ctr`/`Num`/`Rec`/`Tup` sub-pattern) is rejected,
+/// since the rule-dispatch pattern system has no way to check or bind it here.
+pub fn desugar_aggregates(book: &mut DefinitionBook) -> Result<(), DesugarError> {
+  let mut def_idx = 0;
+  while def_idx < book.defs.len() {
+    let mut rule_idx = 0;
+    while rule_idx < book.defs[def_idx].rules.len() {
+      let rule = &mut book.defs[def_idx].rules[rule_idx];
+      let span = rule.span;
+      let mut body = std::mem::replace(&mut rule.body, Term::Era { span });
+      for (pat_idx, pat) in rule.pats.iter_mut().enumerate() {
+        if matches!(pat, Pattern::Rec(..) | Pattern::Tup(..)) {
+          let scrutinee = Name::new(&format!("_arg{pat_idx}"));
+          let names = pattern_field_binds(pat, &scrutinee)?;
+          body = wrap_field_binds(&names, &scrutinee, body, pat.span());
+          *pat = Pattern::Var(Some(scrutinee), pat.span());
+        }
+      }
+      lower_term_aggregates(&mut body);
+      book.defs[def_idx].rules[rule_idx].body = body;
+      rule_idx += 1;
+    }
+    def_idx += 1;
+  }
+  Ok(())
+}
+
+/// The names a record/tuple pattern's fields bind in the rule body, in the
+/// canonical order `Term::Rec`/`Term::Tup` lower their values in: fields sorted
+/// by name for `Pattern::Rec`, left to right for `Pattern::Tup`. A field whose
+/// sub-pattern is `Var(None, _)` falls back to binding the field's own name
+/// (or, for a tuple element, a uniquely-named discard derived from `scrutinee`
+/// and the element's position), since tuple elements have no name of their own.
+/// The discard must be unique per position: reusing the same name for two
+/// simultaneous bindings in one `Dup` chain would alias them. Any other
+/// sub-pattern nests destructuring this desugaring can't express, and is rejected.
+fn pattern_field_binds(pat: &Pattern, scrutinee: &Name) -> Result<Vec<Name>, DesugarError> {
+  match pat {
+    Pattern::Rec(fields, _) => {
+      let mut fields = fields.clone();
+      fields.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+      fields
+        .into_iter()
+        .map(|(nam, sub)| match sub {
+          Pattern::Var(Some(bind), _) => Ok(bind),
+          Pattern::Var(None, _) => Ok(nam),
+          other => Err(DesugarError::UnsupportedNestedPattern(other)),
+        })
+        .collect()
+    }
+    Pattern::Tup(elems, _) => elems
+      .iter()
+      .enumerate()
+      .map(|(i, sub)| match sub {
+        Pattern::Var(Some(bind), _) => Ok(bind.clone()),
+        Pattern::Var(None, _) => Ok(Name::new(&format!("{}_discard{i}", scrutinee.as_str()))),
+        other => Err(DesugarError::UnsupportedNestedPattern(other.clone())),
+      })
+      .collect(),
+    _ => Ok(Vec::new()),
+  }
+}
+
+/// Wraps `body` with the bindings that project `scrutinee`'s fields out by
+/// position: a single field is bound with a plain `Let`, and two or more are
+/// peeled off with a chain of `Dup`s, mirroring how `Term::Tup`/`Term::Rec`
+/// literals are built up from `Sup` in the first place.
+fn wrap_field_binds(names: &[Name], scrutinee: &Name, body: Term, span: Span) -> Term {
+  match names {
+    [] => body,
+    [only] => {
+      Term::Let { nam: only.clone(), val: Box::new(Term::Var { nam: scrutinee.clone(), idx: 0, span }), nxt: Box::new(body), span }
+    }
+    [first, rest @ ..] if rest.len() == 1 => Term::Dup {
+      fst: Some(first.clone()),
+      snd: Some(rest[0].clone()),
+      val: Box::new(Term::Var { nam: scrutinee.clone(), idx: 0, span }),
+      nxt: Box::new(body),
+      span,
+    },
+    [first, rest @ ..] => {
+      let tail = Name::new(&format!("{}_tl", scrutinee.as_str()));
+      let inner = wrap_field_binds(rest, &tail, body, span);
+      Term::Dup {
+        fst: Some(first.clone()),
+        snd: Some(tail),
+        val: Box::new(Term::Var { nam: scrutinee.clone(), idx: 0, span }),
+        nxt: Box::new(inner),
+        span,
+      }
+    }
+  }
+}
+
+/// Recursively lowers every `Term::Rec`/`Term::Tup` reachable from `term` into
+/// a `Sup` chain, innermost aggregates first.
+fn lower_term_aggregates(term: &mut Term) {
+  match term {
+    Term::Lam { bod, .. } | Term::Chn { bod, .. } => lower_term_aggregates(bod),
+    Term::Let { val, nxt, .. } => {
+      lower_term_aggregates(val);
+      lower_term_aggregates(nxt);
+    }
+    Term::If { cond, then, els_, .. } => {
+      lower_term_aggregates(cond);
+      lower_term_aggregates(then);
+      lower_term_aggregates(els_);
+    }
+    Term::App { fun, arg, .. } => {
+      lower_term_aggregates(fun);
+      lower_term_aggregates(arg);
+    }
+    Term::Dup { val, nxt, .. } => {
+      lower_term_aggregates(val);
+      lower_term_aggregates(nxt);
+    }
+    Term::Sup { fst, snd, .. } | Term::Opx { fst, snd, .. } => {
+      lower_term_aggregates(fst);
+      lower_term_aggregates(snd);
+    }
+    Term::Rec { .. } => {
+      let span = term.span();
+      if let Term::Rec { mut fields, .. } = std::mem::replace(term, Term::Era { span }) {
+        fields.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+        let values = fields
+          .into_iter()
+          .map(|(_, mut val)| {
+            lower_term_aggregates(&mut val);
+            val
+          })
+          .collect();
+        *term = to_sup_chain(values, span);
+      }
+    }
+    Term::Tup { .. } => {
+      let span = term.span();
+      if let Term::Tup { mut elems, .. } = std::mem::replace(term, Term::Era { span }) {
+        for elem in &mut elems {
+          lower_term_aggregates(elem);
+        }
+        *term = to_sup_chain(elems, span);
+      }
+    }
+    Term::Var { .. } | Term::Lnk { .. } | Term::Ref { .. } | Term::Era { .. } | Term::Num { .. } | Term::Import { .. } => (),
+  }
+}
+
+/// Folds a list of values into a right-nested `Sup` chain: `[a, b, c]` becomes
+/// `{a {b c}}`. An empty list lowers to `Era`; a single value needs no pairing
+/// and is returned as-is.
+fn to_sup_chain(mut values: Vec<Term>, span: Span) -> Term {
+  match values.len() {
+    0 => Term::Era { span },
+    1 => values.pop().unwrap(),
+    _ => {
+      let last = values.pop().unwrap();
+      values.into_iter().rev().fold(last, |acc, val| Term::Sup { fst: Box::new(val), snd: Box::new(acc), span })
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn var(nam: &str, idx: usize) -> Term {
+    Term::Var { nam: Name::new(nam), idx, span: Span::default() }
+  }
+
+  fn lam(nam: &str, bod: Term) -> Term {
+    Term::Lam { nam: Some(Name::new(nam)), bod: Box::new(bod), implicit: false, span: Span::default() }
+  }
+
+  #[test]
+  fn subst_avoids_capture_under_a_differently_named_binder() {
+    // (λx. λy. x) y: substituting `x` with the free `y` must not let the inner
+    // λy rebind it, so the result should be λy. y@1 (pointing past that binder).
+    let mut body = lam("y", var("x", 0));
+    body.subst((Name::new("x"), 0), &var("y", 0));
+    let Term::Lam { bod, .. } = body else { panic!("expected a Lam") };
+    let Term::Var { nam, idx, .. } = *bod else { panic!("expected a Var") };
+    assert_eq!(nam, Name::new("y"));
+    assert_eq!(idx, 1);
+  }
+
+  #[test]
+  fn eval_opx_rejects_division_by_zero() {
+    assert!(matches!(eval_opx(Op::DIV, Numeric::U24(1), Numeric::U24(0)), Err(OpError::DivByZero(Op::DIV))));
+    assert!(matches!(eval_opx(Op::MOD, Numeric::I24(1), Numeric::I24(0)), Err(OpError::DivByZero(Op::MOD))));
+  }
+
+  #[test]
+  fn eval_opx_rejects_out_of_range_shifts() {
+    assert!(matches!(eval_opx(Op::LSH, Numeric::U24(1), Numeric::U24(32)), Err(OpError::ShiftOverflow(Op::LSH, 32))));
+    assert!(eval_opx(Op::LSH, Numeric::U24(1), Numeric::U24(31)).is_ok());
+  }
+
+  /// A definition `g` with `implicit_arity: 2` out of a total arity of 3.
+  fn book_with_implicit_def() -> (DefinitionBook, DefId) {
+    let mut book = DefinitionBook::new();
+    let def_id = book.def_names.insert(Name::new("g"));
+    let pats = vec![Pattern::Var(None, Span::default()); 3];
+    let rule = Rule { def_id, pats, body: Term::Era { span: Span::default() }, span: Span::default() };
+    book.defs.push(Definition { def_id, rules: vec![rule], span: Span::default(), implicit_arity: 2 });
+    (book, def_id)
+  }
+
+  #[test]
+  fn elaborate_implicits_fills_only_the_still_missing_slots() {
+    // (g {x} y): one implicit arg already supplied manually, so only the
+    // remaining implicit slot should be synthesized, not both.
+    let (book, def_id) = book_with_implicit_def();
+    let mut term = Term::App {
+      fun: Box::new(Term::App {
+        fun: Box::new(Term::Ref { def_id, span: Span::default() }),
+        arg: Box::new(var("x", 0)),
+        implicit: true,
+        span: Span::default(),
+      }),
+      arg: Box::new(var("y", 0)),
+      implicit: false,
+      span: Span::default(),
+    };
+    elaborate_term(&mut term, &book);
+    let (_, args) = take_spine(&mut term);
+    assert_eq!(args.len(), 3);
+    assert!(matches!(&args[0], (Term::Era { .. }, true, _)));
+    assert!(matches!(&args[1], (Term::Var { nam, .. }, true, _) if *nam == Name::new("x")));
+    assert!(matches!(&args[2], (Term::Var { nam, .. }, false, _) if *nam == Name::new("y")));
+  }
+
+  #[test]
+  fn desugar_aggregates_rejects_nested_destructuring_in_a_field_pattern() {
+    // `{x = Some(y)}` nests a constructor check inside a record field, which
+    // this desugaring can't express as a `Dup` projection; it must error
+    // instead of silently dropping the `Some` tag and leaving `y` unbound.
+    let span = Span::default();
+    let sub = Pattern::Ctr(Name::new("Some"), vec![Pattern::Var(Some(Name::new("y")), span)], span);
+    let pat = Pattern::Rec(vec![(Name::new("x"), sub)], span);
+    let mut book = DefinitionBook::new();
+    let def_id = book.def_names.insert(Name::new("f"));
+    let rule = Rule { def_id, pats: vec![pat], body: var("y", 0), span };
+    book.defs.push(Definition { def_id, rules: vec![rule], span, implicit_arity: 0 });
+    assert!(matches!(desugar_aggregates(&mut book), Err(DesugarError::UnsupportedNestedPattern(_))));
+  }
+}